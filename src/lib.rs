@@ -22,7 +22,20 @@ const RESOURCES: &[&str] = &[
 ];
 const BATCH_SIZE: usize = 50;
 const DEFAULT_QUERY_LIMIT: i64 = 250;
+// Keyset pagination fetches `limit + 1` rows to detect a next page; cap the
+// client-supplied limit well clear of i64::MAX so that addition can't wrap.
+const MAX_QUERY_LIMIT: i64 = 1000;
 const SYNC_PAGE_SIZE: i64 = 1000;
+// PeeringDB mutates slowly, so a half-hour edge cache is cheap; past the
+// refetch window we still serve the stale copy but kick off a background
+// refresh rather than making the client wait on D1.
+const DEFAULT_CACHE_TTL_SECS: u64 = 1800;
+const CACHE_REFETCH_AFTER_SECS: u64 = 900;
+const CACHED_AT_HEADER: &str = "X-PDB-Cached-At";
+// /metrics only ever reads the latest sync_runs row per resource; keep a
+// handful of older ones around for debugging without letting the table grow
+// unbounded.
+const SYNC_RUN_RETENTION: i64 = 20;
 const USER_AGENT: &str = concat!(
     "peeringdb-mirror/",
     env!("CARGO_PKG_VERSION"),
@@ -39,6 +52,29 @@ struct PayloadRow {
     payload: String,
 }
 
+#[derive(Deserialize)]
+struct IdPayloadRow {
+    obj_id: i64,
+    payload: String,
+}
+
+#[derive(Deserialize)]
+struct BatchSubRequest {
+    resource: String,
+    #[serde(default)]
+    ids: Vec<i64>,
+    since: Option<i64>,
+    limit: Option<i64>,
+    #[serde(default)]
+    include_deleted: bool,
+}
+
+#[derive(Serialize)]
+struct BatchSubResult {
+    resource: String,
+    data: Vec<Value>,
+}
+
 #[derive(Serialize)]
 struct SyncReport {
     resource: String,
@@ -50,13 +86,140 @@ struct TsRow {
     ts: Option<String>,
 }
 
+/// Internal error type mapped to a `{"error":{"code","message"}}` body and
+/// the matching HTTP status, instead of letting failures fall through to a
+/// blanket 500 via `Error::RustError`.
+#[derive(Debug)]
+enum ApiError {
+    UnknownResource,
+    BadParam(&'static str),
+    Unauthorized,
+    Upstream(u16),
+    // The upstream request never got far enough to produce a status (DNS,
+    // connect, timeout) — kept distinct from `DbUnavailable` so operators can
+    // tell a PeeringDB connectivity problem apart from a D1 outage.
+    UpstreamUnreachable,
+    DbUnavailable,
+    // Reserved for when we start throttling our own API; nothing raises it yet.
+    #[allow(dead_code)]
+    RateLimited,
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+impl ApiError {
+    fn status(&self) -> u16 {
+        match self {
+            ApiError::UnknownResource => 400,
+            ApiError::BadParam(_) => 400,
+            ApiError::Unauthorized => 401,
+            ApiError::Upstream(status) if *status == 429 => 503,
+            ApiError::Upstream(_) => 502,
+            ApiError::UpstreamUnreachable => 502,
+            ApiError::DbUnavailable => 503,
+            ApiError::RateLimited => 429,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::UnknownResource => "unknown_resource",
+            ApiError::BadParam(_) => "bad_param",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Upstream(_) => "upstream_error",
+            ApiError::UpstreamUnreachable => "upstream_unreachable",
+            ApiError::DbUnavailable => "db_unavailable",
+            ApiError::RateLimited => "rate_limited",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::UnknownResource => "unknown resource".to_string(),
+            ApiError::BadParam(msg) => msg.to_string(),
+            ApiError::Unauthorized => "unauthorized".to_string(),
+            ApiError::Upstream(status) => format!("upstream returned status {}", status),
+            ApiError::UpstreamUnreachable => "could not reach upstream".to_string(),
+            ApiError::DbUnavailable => "database temporarily unavailable".to_string(),
+            ApiError::RateLimited => "rate limit exceeded".to_string(),
+        }
+    }
+
+    fn into_response(&self) -> Result<Response> {
+        let body = json!({ "error": { "code": self.code(), "message": self.message() } });
+        Ok(Response::from_json(&body)?.with_status(self.status()))
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        console_error!("internal error: {:?}", err);
+        ApiError::DbUnavailable
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        console_error!("serde error: {:?}", err);
+        ApiError::DbUnavailable
+    }
+}
+
+#[derive(Deserialize)]
+struct ResourceCountRow {
+    resource: String,
+    cnt: i64,
+}
+
+#[derive(Deserialize)]
+struct SyncRunRow {
+    resource: String,
+    imported: i64,
+}
+
 #[event(fetch, respond_with_errors)]
-pub async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
+pub async fn main(req: Request, env: Env, worker_ctx: Context) -> Result<Response> {
+    let ctx_for_id = worker_ctx.clone();
+    let ctx_for_query = worker_ctx.clone();
     Router::new()
-        .get_async("/api/:resource/:id", |_, ctx| async move { get_by_id(ctx).await })
-        .get_async("/api/:resource", |req, ctx| async move { query_resource(req, ctx).await })
+        .options("/api/:resource/:id", |req, ctx| preflight_response(&req, &ctx.env))
+        .options("/api/:resource", |req, ctx| preflight_response(&req, &ctx.env))
+        .options("/api/batch", |req, ctx| preflight_response(&req, &ctx.env))
+        .get_async("/api/:resource/:id", move |req, ctx| {
+            let worker_ctx = ctx_for_id.clone();
+            async move {
+                let origin = req.headers().get("Origin")?;
+                let env = ctx.env.clone();
+                let resp = get_by_id_cached(req, ctx, worker_ctx)
+                    .await
+                    .or_else(|err| err.into_response())?;
+                with_cors(resp, origin.as_deref(), &env)
+            }
+        })
+        .get_async("/api/:resource", move |req, ctx| {
+            let worker_ctx = ctx_for_query.clone();
+            async move {
+                let origin = req.headers().get("Origin")?;
+                let env = ctx.env.clone();
+                let resp = query_resource_cached(req, ctx, worker_ctx)
+                    .await
+                    .or_else(|err| err.into_response())?;
+                with_cors(resp, origin.as_deref(), &env)
+            }
+        })
         .get("/health", |_, _| Response::ok("ok"))
-        .post_async("/admin/sync", |req, ctx| async move { run_sync(req, ctx).await })
+        .get_async("/metrics", |_, ctx| async move { metrics(ctx).await })
+        .post_async("/api/batch", |req, ctx| async move {
+            let origin = req.headers().get("Origin")?;
+            let env = ctx.env.clone();
+            let resp = batch_get(req, ctx)
+                .await
+                .or_else(|err| err.into_response())?;
+            with_cors(resp, origin.as_deref(), &env)
+        })
+        .post_async("/admin/sync", |req, ctx| async move {
+            run_sync(req, ctx).await.or_else(|err| err.into_response())
+        })
         .run(req, env)
         .await
 }
@@ -68,69 +231,206 @@ pub async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext)
     }
 }
 
-async fn get_by_id(ctx: RouteContext<()>) -> Result<Response> {
+async fn get_by_id_cached(
+    req: Request,
+    ctx: RouteContext<()>,
+    worker_ctx: Context,
+) -> ApiResult<Response> {
     let resource = ctx
         .param("resource")
-        .ok_or_else(|| Error::RustError("resource missing".into()))?;
-    if !RESOURCES.contains(&resource.as_str()) {
-        return Response::error("unknown resource", 400);
-    }
-    let id_raw = ctx
-        .param("id")
-        .ok_or_else(|| Error::RustError("id missing".into()))?;
+        .ok_or(ApiError::BadParam("resource missing"))?
+        .clone();
+    let id_raw = ctx.param("id").ok_or(ApiError::BadParam("id missing"))?;
     let id: i64 = id_raw
         .parse()
-        .map_err(|_| Error::RustError("id must be an integer".into()))?;
+        .map_err(|_| ApiError::BadParam("id must be an integer"))?;
 
-    let db = ctx.env.d1("PEERINGDB")?;
-    let statement = db.prepare("SELECT payload FROM objects WHERE resource = ?1 AND obj_id = ?2");
-    let query = statement.bind(&[
-        JsValue::from_str(&resource),
-        JsValue::from_f64(id as f64),
-    ])?;
+    if !RESOURCES.contains(&resource.as_str()) {
+        return Err(ApiError::UnknownResource);
+    }
+    let include_deleted = req
+        .url()?
+        .query_pairs()
+        .any(|(k, v)| k == "include_deleted" && v == "1");
+
+    let env = ctx.env.clone();
+    let cache = Cache::default();
+    let cache_key = req.clone()?;
+
+    if let Some(cached) = cache.get(&cache_key, true).await? {
+        if is_stale(&cached) {
+            let refresh_key = req.clone()?;
+            let refresh_env = env.clone();
+            let refresh_resource = resource.clone();
+            worker_ctx.wait_until(async move {
+                if let Ok(resp) =
+                    fetch_by_id(&refresh_env, &refresh_resource, id, include_deleted).await
+                {
+                    let ttl = cache_ttl_secs(&refresh_env);
+                    if let Ok(stamped) = stamp_for_cache(resp, ttl) {
+                        if let Ok(to_store) = stamped.cloned() {
+                            let _ = Cache::default().put(&refresh_key, to_store).await;
+                        }
+                    }
+                }
+            });
+        }
+        return Ok(cached);
+    }
+
+    let resp = fetch_by_id(&env, &resource, id, include_deleted).await?;
+    let ttl = cache_ttl_secs(&env);
+    let stamped = stamp_for_cache(resp, ttl)?;
+    if let Ok(to_store) = stamped.cloned() {
+        let _ = cache.put(&cache_key, to_store).await;
+    }
+    Ok(stamped)
+}
+
+async fn fetch_by_id(env: &Env, resource: &str, id: i64, include_deleted: bool) -> ApiResult<Response> {
+    let db = env.d1("PEERINGDB")?;
+    let mut sql = String::from("SELECT payload FROM objects WHERE resource = ?1 AND obj_id = ?2");
+    if !include_deleted {
+        sql.push_str(" AND deleted = 0");
+    }
+    let statement = db.prepare(&sql);
+    let query = statement.bind(&[JsValue::from_str(resource), JsValue::from_f64(id as f64)])?;
     let row = query.first::<PayloadRow>(None).await?;
 
     if let Some(row) = row {
         let payload: Value = serde_json::from_str(&row.payload)?;
-        json_response(vec![payload])
+        Ok(json_response(vec![payload])?)
     } else {
-        json_response(Vec::new())
+        Ok(json_response(Vec::new())?)
     }
 }
 
-async fn query_resource(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+struct ResourceQuery {
+    resource: String,
+    id: Option<i64>,
+    since: Option<i64>,
+    limit: i64,
+    include_deleted: bool,
+    cursor: Option<i64>,
+}
+
+fn parse_resource_query(req: &Request, ctx: &RouteContext<()>) -> ApiResult<ResourceQuery> {
     let resource = ctx
         .param("resource")
-        .ok_or_else(|| Error::RustError("resource missing".into()))?;
+        .ok_or(ApiError::BadParam("resource missing"))?
+        .clone();
     if !RESOURCES.contains(&resource.as_str()) {
-        return Response::error("unknown resource", 400);
+        return Err(ApiError::UnknownResource);
     }
     let url = req.url()?;
 
-    let mut id_filter: Option<i64> = None;
-    let mut since_filter: Option<i64> = None;
-    let mut limit: i64 = DEFAULT_QUERY_LIMIT;
+    let mut id = None;
+    let mut since = None;
+    let mut limit = DEFAULT_QUERY_LIMIT;
+    let mut include_deleted = false;
+    let mut cursor = None;
 
     for (key, value) in url.query_pairs() {
         match key.as_ref() {
-            "id" => id_filter = value.parse().ok(),
-            "since" => since_filter = value.parse().ok(),
-            "limit" => limit = value.parse::<i64>().unwrap_or(limit),
+            "id" => {
+                id = Some(
+                    value
+                        .parse()
+                        .map_err(|_| ApiError::BadParam("id must be an integer"))?,
+                )
+            }
+            "since" => {
+                since = Some(
+                    value
+                        .parse()
+                        .map_err(|_| ApiError::BadParam("since must be an integer"))?,
+                )
+            }
+            "limit" => {
+                limit = value
+                    .parse()
+                    .map_err(|_| ApiError::BadParam("limit must be an integer"))?
+            }
+            "include_deleted" => include_deleted = value == "1",
+            "cursor" => {
+                cursor = Some(
+                    value
+                        .parse()
+                        .map_err(|_| ApiError::BadParam("cursor is not a valid opaque cursor"))?,
+                )
+            }
             _ => {}
         }
     }
 
-    let db = ctx.env.d1("PEERINGDB")?;
-    let mut sql = String::from("SELECT payload FROM objects WHERE resource = ?1");
-    let mut bindings: Vec<JsValue> = vec![JsValue::from_str(&resource)];
+    // Keyset pagination adds 1 to this before binding it as a LIMIT; clamp
+    // here (not just floor) so a huge client-supplied value can't wrap that
+    // addition into a negative LIMIT, which SQLite reads as "no limit".
+    let limit = limit.clamp(1, MAX_QUERY_LIMIT);
+
+    Ok(ResourceQuery {
+        resource,
+        id,
+        since,
+        limit,
+        include_deleted,
+        cursor,
+    })
+}
 
-    if let Some(id) = id_filter {
+async fn query_resource_cached(
+    req: Request,
+    ctx: RouteContext<()>,
+    worker_ctx: Context,
+) -> ApiResult<Response> {
+    let parsed = parse_resource_query(&req, &ctx)?;
+    let env = ctx.env.clone();
+    let cache = Cache::default();
+    let cache_key = req.clone()?;
+
+    if let Some(cached) = cache.get(&cache_key, true).await? {
+        if is_stale(&cached) {
+            let refresh_key = req.clone()?;
+            let refresh_env = env.clone();
+            worker_ctx.wait_until(async move {
+                if let Ok(resp) = fetch_query(&refresh_env, &parsed).await {
+                    let ttl = cache_ttl_secs(&refresh_env);
+                    if let Ok(stamped) = stamp_for_cache(resp, ttl) {
+                        if let Ok(to_store) = stamped.cloned() {
+                            let _ = Cache::default().put(&refresh_key, to_store).await;
+                        }
+                    }
+                }
+            });
+        }
+        return Ok(cached);
+    }
+
+    let resp = fetch_query(&env, &parsed).await?;
+    let ttl = cache_ttl_secs(&env);
+    let stamped = stamp_for_cache(resp, ttl)?;
+    if let Ok(to_store) = stamped.cloned() {
+        let _ = cache.put(&cache_key, to_store).await;
+    }
+    Ok(stamped)
+}
+
+async fn fetch_query(env: &Env, parsed: &ResourceQuery) -> ApiResult<Response> {
+    let db = env.d1("PEERINGDB")?;
+    let mut sql = String::from("SELECT obj_id, payload FROM objects WHERE resource = ?1");
+    let mut bindings: Vec<JsValue> = vec![JsValue::from_str(&parsed.resource)];
+
+    if !parsed.include_deleted {
+        sql.push_str(" AND deleted = 0");
+    }
+
+    if let Some(id) = parsed.id {
         let idx = bindings.len() + 1;
         sql.push_str(&format!(" AND obj_id = ?{}", idx));
         bindings.push(JsValue::from_f64(id as f64));
     }
 
-    if let Some(since_ts) = since_filter {
+    if let Some(since_ts) = parsed.since {
         let idx = bindings.len() + 1;
         sql.push_str(&format!(
             " AND datetime(updated) > datetime(?{}, 'unixepoch')",
@@ -139,59 +439,221 @@ async fn query_resource(req: Request, ctx: RouteContext<()>) -> Result<Response>
         bindings.push(JsValue::from_f64(since_ts as f64));
     }
 
+    if let Some(cursor) = parsed.cursor {
+        let idx = bindings.len() + 1;
+        sql.push_str(&format!(" AND obj_id > ?{}", idx));
+        bindings.push(JsValue::from_f64(cursor as f64));
+    }
+
+    // Keyset pagination: fetch one extra row to learn whether the page is
+    // full without a separate COUNT(*) query. `parsed.limit` is already
+    // clamped to MAX_QUERY_LIMIT by parse_resource_query.
+    let page_size = parsed.limit;
     let idx = bindings.len() + 1;
     sql.push_str(&format!(" ORDER BY obj_id LIMIT ?{}", idx));
-    bindings.push(JsValue::from_f64(limit.max(1) as f64));
+    bindings.push(JsValue::from_f64((page_size + 1) as f64));
 
     let statement = db.prepare(&sql);
     let query = statement.bind(&bindings)?;
     let result = query.all().await?;
-    let rows: Vec<PayloadRow> = result.results()?;
+    let mut rows: Vec<IdPayloadRow> = result.results()?;
+
+    let next_cursor = if rows.len() as i64 > page_size {
+        rows.truncate(page_size as usize);
+        rows.last().map(|row| row.obj_id)
+    } else {
+        None
+    };
+
     let payloads: Vec<Value> = rows
         .into_iter()
         .map(|row| serde_json::from_str(&row.payload))
         .collect::<std::result::Result<_, _>>()?;
 
-    json_response(payloads)
+    Ok(json_response_with_cursor(payloads, next_cursor)?)
+}
+
+async fn batch_get(mut req: Request, ctx: RouteContext<()>) -> ApiResult<Response> {
+    let subs: Vec<BatchSubRequest> = req
+        .json()
+        .await
+        .map_err(|_| ApiError::BadParam("invalid batch request body"))?;
+    for sub in &subs {
+        if !RESOURCES.contains(&sub.resource.as_str()) {
+            return Err(ApiError::UnknownResource);
+        }
+    }
+
+    let db = ctx.env.d1("PEERINGDB")?;
+    let mut statements: Vec<D1PreparedStatement> = Vec::with_capacity(subs.len());
+    for sub in &subs {
+        let mut sql = String::from("SELECT payload FROM objects WHERE resource = ?1");
+        let mut bindings: Vec<JsValue> = vec![JsValue::from_str(&sub.resource)];
+
+        if !sub.include_deleted {
+            sql.push_str(" AND deleted = 0");
+        }
+
+        if !sub.ids.is_empty() {
+            let placeholders: Vec<String> = sub
+                .ids
+                .iter()
+                .map(|id| {
+                    let idx = bindings.len() + 1;
+                    bindings.push(JsValue::from_f64(*id as f64));
+                    format!("?{}", idx)
+                })
+                .collect();
+            sql.push_str(&format!(" AND obj_id IN ({})", placeholders.join(",")));
+        } else if let Some(since_ts) = sub.since {
+            let idx = bindings.len() + 1;
+            sql.push_str(&format!(
+                " AND datetime(updated) > datetime(?{}, 'unixepoch')",
+                idx
+            ));
+            bindings.push(JsValue::from_f64(since_ts as f64));
+        }
+
+        let idx = bindings.len() + 1;
+        sql.push_str(&format!(" ORDER BY obj_id LIMIT ?{}", idx));
+        bindings.push(JsValue::from_f64(
+            sub.limit.unwrap_or(DEFAULT_QUERY_LIMIT).max(1) as f64,
+        ));
+
+        statements.push(db.prepare(&sql).bind(&bindings)?);
+    }
+
+    let batch_results = db.batch(statements).await?;
+    let mut results = Vec::with_capacity(subs.len());
+    for (sub, result) in subs.into_iter().zip(batch_results.into_iter()) {
+        let rows: Vec<PayloadRow> = result.results()?;
+        let data: Vec<Value> = rows
+            .into_iter()
+            .map(|row| serde_json::from_str(&row.payload))
+            .collect::<std::result::Result<_, _>>()?;
+        results.push(BatchSubResult {
+            resource: sub.resource,
+            data,
+        });
+    }
+
+    Ok(Response::from_json(&json!({ "results": results }))?)
 }
 
-async fn run_sync(req: Request, ctx: RouteContext<()>) -> Result<Response> {
+async fn run_sync(req: Request, ctx: RouteContext<()>) -> ApiResult<Response> {
     let secret = ctx.secret("SYNC_SECRET")?;
     let expected = format!("Bearer {}", secret.to_string());
     let authorized = matches!(req.headers().get("Authorization")?, Some(header) if header == expected);
     if !authorized {
-        return Response::error("unauthorized", 401);
+        return Err(ApiError::Unauthorized);
     }
 
-    let resource_filter = req
-        .url()
-        .ok()
-        .and_then(|u| {
-            u.query_pairs()
-                .find(|(k, _)| k == "resource")
-                .map(|(_, v)| v.to_string())
-        })
-        .and_then(|r| {
-            if RESOURCES.contains(&r.as_str()) {
-                Some(vec![r])
-            } else {
-                None
-            }
-        });
+    let resource_param = req
+        .url()?
+        .query_pairs()
+        .find(|(k, _)| k == "resource")
+        .map(|(_, v)| v.to_string());
 
-    let resources: Vec<&str> = resource_filter
-        .as_ref()
-        .map(|v| v.iter().map(|s| s.as_str()).collect())
-        .unwrap_or_else(|| RESOURCES.to_vec());
+    let resources: Vec<&str> = match resource_param {
+        Some(r) => {
+            if !RESOURCES.contains(&r.as_str()) {
+                return Err(ApiError::UnknownResource);
+            }
+            vec![RESOURCES.iter().copied().find(|&res| res == r).unwrap()]
+        }
+        None => RESOURCES.to_vec(),
+    };
 
     let reports = sync_all(&ctx.env, &resources).await?;
-    Response::from_json(&json!({ "synced": reports }))
+    Ok(Response::from_json(&json!({ "synced": reports }))?)
+}
+
+async fn metrics(ctx: RouteContext<()>) -> Result<Response> {
+    let db = ctx.env.d1("PEERINGDB")?;
+    let mut out = String::new();
+
+    out.push_str("# HELP peeringdb_objects_total Number of mirrored objects per resource.\n");
+    out.push_str("# TYPE peeringdb_objects_total gauge\n");
+    let counts: Vec<ResourceCountRow> = db
+        .prepare("SELECT resource, COUNT(*) as cnt FROM objects WHERE deleted = 0 GROUP BY resource")
+        .bind(&[])?
+        .all()
+        .await?
+        .results()?;
+    for row in &counts {
+        out.push_str(&format!(
+            "peeringdb_objects_total{{resource=\"{}\"}} {}\n",
+            row.resource, row.cnt
+        ));
+    }
+
+    out.push_str("# HELP peeringdb_last_synced_epoch Unix epoch of the newest record seen per resource.\n");
+    out.push_str("# TYPE peeringdb_last_synced_epoch gauge\n");
+    for resource in RESOURCES {
+        if let Some(ts) = max_updated_epoch(&db, resource).await? {
+            out.push_str(&format!(
+                "peeringdb_last_synced_epoch{{resource=\"{}\"}} {}\n",
+                resource, ts
+            ));
+        }
+    }
+
+    out.push_str("# HELP peeringdb_last_sync_imported_total Objects imported by the most recent sync run per resource.\n");
+    out.push_str("# TYPE peeringdb_last_sync_imported_total counter\n");
+    let last_runs: Vec<SyncRunRow> = db
+        .prepare(
+            "SELECT resource, imported FROM sync_runs \
+             WHERE ran_at = (SELECT MAX(ran_at) FROM sync_runs AS s WHERE s.resource = sync_runs.resource)",
+        )
+        .bind(&[])?
+        .all()
+        .await?
+        .results()?;
+    for row in &last_runs {
+        out.push_str(&format!(
+            "peeringdb_last_sync_imported_total{{resource=\"{}\"}} {}\n",
+            row.resource, row.imported
+        ));
+    }
+
+    let mut headers = Headers::new();
+    headers.set("Content-Type", "text/plain; version=0.0.4")?;
+    Ok(Response::ok(out)?.with_headers(headers))
+}
+
+// Telemetry only: a failed write here must never take down a sync run, so
+// callers log-and-continue instead of propagating this as a hard error.
+async fn record_sync_run(db: &D1Database, resource: &str, imported: usize) -> Result<()> {
+    let now_secs = (Date::now() / 1000.0) as i64;
+    db.prepare("INSERT INTO sync_runs (resource, imported, ran_at) VALUES (?1, ?2, ?3)")
+        .bind(&[
+            JsValue::from_str(resource),
+            JsValue::from_f64(imported as f64),
+            JsValue::from_f64(now_secs as f64),
+        ])?
+        .run()
+        .await?;
+
+    // Keep the table bounded: /metrics only ever reads the latest run per
+    // resource, so only the newest SYNC_RUN_RETENTION rows are kept.
+    db.prepare(
+        "DELETE FROM sync_runs WHERE resource = ?1 AND ran_at NOT IN \
+         (SELECT ran_at FROM sync_runs WHERE resource = ?1 ORDER BY ran_at DESC LIMIT ?2)",
+    )
+    .bind(&[
+        JsValue::from_str(resource),
+        JsValue::from_f64(SYNC_RUN_RETENTION as f64),
+    ])?
+    .run()
+    .await?;
+
+    Ok(())
 }
 
 async fn sync_all(
     env: &Env,
     resources: &[&str],
-) -> Result<Vec<SyncReport>> {
+) -> ApiResult<Vec<SyncReport>> {
     let mut reports = Vec::new();
     for resource in resources {
         let report = sync_resource(env, resource).await?;
@@ -204,33 +666,38 @@ async fn sync_all(
 async fn sync_resource(
     env: &Env,
     resource: &str,
-) -> Result<SyncReport> {
+) -> ApiResult<SyncReport> {
     let db = env.d1("PEERINGDB")?;
     let since = max_updated_epoch(&db, resource).await?;
 
     // Prefer incremental when we have a previous max(updated); fall back to full snapshot.
     let imported = match since {
-        Some(since_ts) => sync_since(&db, resource, since_ts).await?,
-        None => sync_full(&db, resource).await?,
+        Some(since_ts) => sync_since(env, &db, resource, since_ts).await?,
+        None => sync_full(env, &db, resource).await?,
     };
 
+    if let Err(err) = record_sync_run(&db, resource, imported).await {
+        console_error!("failed to record sync telemetry for {}: {:?}", resource, err);
+    }
+
     Ok(SyncReport {
         resource: resource.to_string(),
         imported,
     })
 }
 
-async fn sync_full(db: &D1Database, resource: &str) -> Result<usize> {
+async fn sync_full(env: &Env, db: &D1Database, resource: &str) -> ApiResult<usize> {
     let url = format!("{}/{}-0.json", PUBLIC_BASE, resource);
     let parsed = fetch_api(&url).await?;
-    upsert_objects(db, resource, &parsed.data).await
+    Ok(upsert_objects(env, db, resource, &parsed.data).await?)
 }
 
 async fn sync_since(
+    env: &Env,
     db: &D1Database,
     resource: &str,
     since_ts: i64,
-) -> Result<usize> {
+) -> ApiResult<usize> {
     let now_secs = (Date::now() / 1000.0) as i64;
     let effective_since = since_ts.min(now_secs);
 
@@ -248,7 +715,7 @@ async fn sync_since(
             break;
         }
 
-        let imported = upsert_objects(db, resource, &parsed.data).await?;
+        let imported = upsert_objects(env, db, resource, &parsed.data).await?;
         total += imported;
 
         if (parsed.data.len() as i64) < limit {
@@ -260,8 +727,14 @@ async fn sync_since(
     Ok(total)
 }
 
-async fn upsert_objects(db: &D1Database, resource: &str, objects: &[Value]) -> Result<usize> {
+async fn upsert_objects(
+    env: &Env,
+    db: &D1Database,
+    resource: &str,
+    objects: &[Value],
+) -> Result<usize> {
     let mut imported = 0usize;
+    let mut touched_ids: Vec<i64> = Vec::with_capacity(objects.len());
     let mut batch: Vec<D1PreparedStatement> = Vec::with_capacity(BATCH_SIZE);
     for obj in objects {
         let id = obj
@@ -272,17 +745,26 @@ async fn upsert_objects(db: &D1Database, resource: &str, objects: &[Value]) -> R
             .get("updated")
             .and_then(|v| v.as_str())
             .unwrap_or("");
+        // PeeringDB's changelog API returns deleted objects in the `since` window
+        // with status bumped to "deleted" rather than omitting them; mark a
+        // tombstone instead of leaving the stale payload visible to readers.
+        let deleted = obj
+            .get("status")
+            .and_then(|v| v.as_str())
+            .is_some_and(|status| status.eq_ignore_ascii_case("deleted"));
         let payload = serde_json::to_string(obj)?;
 
         let prepared = db
-            .prepare("INSERT INTO objects (resource, obj_id, updated, payload) VALUES (?1, ?2, ?3, ?4) ON CONFLICT(resource, obj_id) DO UPDATE SET updated = excluded.updated, payload = excluded.payload")
+            .prepare("INSERT INTO objects (resource, obj_id, updated, payload, deleted) VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(resource, obj_id) DO UPDATE SET updated = excluded.updated, payload = excluded.payload, deleted = excluded.deleted")
             .bind(&[
                 JsValue::from_str(resource),
                 JsValue::from_f64(id as f64),
                 JsValue::from_str(updated),
                 JsValue::from_str(&payload),
+                JsValue::from_bool(deleted),
             ])?;
         batch.push(prepared);
+        touched_ids.push(id);
         imported += 1;
 
         if batch.len() >= BATCH_SIZE {
@@ -295,9 +777,38 @@ async fn upsert_objects(db: &D1Database, resource: &str, objects: &[Value]) -> R
         db.batch(batch).await?;
     }
 
+    if imported > 0 {
+        bust_resource_cache(env, resource, &touched_ids).await;
+    }
+
     Ok(imported)
 }
 
+/// Best-effort edge cache invalidation for the list and per-id routes of a
+/// resource that just changed. Requires `PUBLIC_WORKER_URL` to be set to the
+/// Worker's own origin, since the Cache API keys entries by full request URL;
+/// without it we just let the TTL expire naturally. Only the bare list URL
+/// and the plain per-id URLs are evicted; entries cached under a query
+/// string (`?limit=`, `?cursor=`, `?since=`, `?include_deleted=1`, ...) stay
+/// until their TTL runs out.
+async fn bust_resource_cache(env: &Env, resource: &str, ids: &[i64]) {
+    let Ok(base) = env.var("PUBLIC_WORKER_URL") else {
+        return;
+    };
+    let base = base.to_string();
+    let cache = Cache::default();
+
+    if let Ok(list_req) = Request::new(&format!("{}/api/{}", base, resource), Method::Get) {
+        let _ = cache.delete(&list_req).await;
+    }
+    for id in ids {
+        if let Ok(item_req) = Request::new(&format!("{}/api/{}/{}", base, resource, id), Method::Get)
+        {
+            let _ = cache.delete(&item_req).await;
+        }
+    }
+}
+
 async fn max_updated_epoch(db: &D1Database, resource: &str) -> Result<Option<i64>> {
     let stmt = db.prepare("SELECT strftime('%s', MAX(updated)) as ts FROM objects WHERE resource = ?1");
     let query = stmt.bind(&[JsValue::from_str(resource)])?;
@@ -305,7 +816,7 @@ async fn max_updated_epoch(db: &D1Database, resource: &str) -> Result<Option<i64
     Ok(row.and_then(|r| r.ts.and_then(|v| v.parse::<i64>().ok())))
 }
 
-async fn fetch_api(url: &str) -> Result<ApiResponse> {
+async fn fetch_api(url: &str) -> ApiResult<ApiResponse> {
     let mut init = RequestInit::new();
     init.with_method(Method::Get);
     let mut request = Request::new_with_init(url, &init)?;
@@ -316,26 +827,99 @@ async fn fetch_api(url: &str) -> Result<ApiResponse> {
         headers.set("User-Agent", USER_AGENT)?;
     }
 
-    let mut resp = Fetch::Request(request).send().await?;
+    let mut resp = Fetch::Request(request).send().await.map_err(|err| {
+        console_error!("failed to reach {}: {:?}", url, err);
+        ApiError::UpstreamUnreachable
+    })?;
 
     let status = resp.status_code();
     if status >= 400 {
         let body = resp.text().await.unwrap_or_else(|_| "<no-body>".into());
-        return Err(Error::RustError(format!(
+        console_error!(
             "status {} from {} body_snip={}",
             status,
             url,
             body.get(..200).unwrap_or(&body)
-        )));
+        );
+        return Err(ApiError::Upstream(status));
     }
 
-    resp.json().await
+    Ok(resp.json().await?)
 }
 
 fn json_response(data: Vec<Value>) -> Result<Response> {
     Response::from_json(&json!({ "meta": {}, "data": data }))
 }
 
+fn json_response_with_cursor(data: Vec<Value>, next_cursor: Option<i64>) -> Result<Response> {
+    let meta = match next_cursor {
+        Some(cursor) => json!({ "next_cursor": cursor.to_string() }),
+        None => json!({}),
+    };
+    Response::from_json(&json!({ "meta": meta, "data": data }))
+}
+
+/// Origins allowed to read the mirror from a browser, configured via the
+/// `CORS_ALLOWED_ORIGINS` var as `*` or a comma-separated allowlist. With no
+/// var set, or no match, no CORS headers are added and cross-origin reads
+/// stay blocked by the browser as before.
+fn allowed_cors_origin(origin: Option<&str>, env: &Env) -> Option<String> {
+    let configured = env.var("CORS_ALLOWED_ORIGINS").ok()?.to_string();
+    if configured.trim() == "*" {
+        return Some("*".to_string());
+    }
+    let origin = origin?;
+    configured
+        .split(',')
+        .map(|allowed| allowed.trim())
+        .find(|&allowed| allowed == origin)
+        .map(|_| origin.to_string())
+}
+
+fn with_cors(resp: Response, origin: Option<&str>, env: &Env) -> Result<Response> {
+    match allowed_cors_origin(origin, env) {
+        Some(allow_origin) => {
+            let mut headers = resp.headers().clone();
+            headers.set("Access-Control-Allow-Origin", &allow_origin)?;
+            headers.set("Access-Control-Allow-Methods", "GET, POST, OPTIONS")?;
+            headers.set("Access-Control-Allow-Headers", "Content-Type, Authorization")?;
+            headers.set("Vary", "Origin")?;
+            Ok(resp.with_headers(headers))
+        }
+        None => Ok(resp),
+    }
+}
+
+fn preflight_response(req: &Request, env: &Env) -> Result<Response> {
+    let origin = req.headers().get("Origin")?;
+    with_cors(Response::ok("")?.with_status(204), origin.as_deref(), env)
+}
+
+fn cache_ttl_secs(env: &Env) -> u64 {
+    env.var("CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.to_string().parse().ok())
+        .unwrap_or(DEFAULT_CACHE_TTL_SECS)
+}
+
+fn stamp_for_cache(resp: Response, ttl_secs: u64) -> Result<Response> {
+    let mut headers = resp.headers().clone();
+    headers.set("Cache-Control", &format!("max-age={}", ttl_secs))?;
+    headers.set(CACHED_AT_HEADER, &((Date::now() / 1000.0) as i64).to_string())?;
+    Ok(resp.with_headers(headers))
+}
+
+fn is_stale(resp: &Response) -> bool {
+    let now = (Date::now() / 1000.0) as i64;
+    match resp.headers().get(CACHED_AT_HEADER).ok().flatten() {
+        Some(cached_at) => match cached_at.parse::<i64>() {
+            Ok(cached_at) => now - cached_at > CACHE_REFETCH_AFTER_SECS as i64,
+            Err(_) => true,
+        },
+        None => true,
+    }
+}
+
 // Worker-build shim checks for this export; we provide a no-op to silence warnings.
 #[wasm_bindgen]
 pub fn set_panic_hook() {}